@@ -0,0 +1,113 @@
+use crate::fetch::KeywordData;
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+const CACHE_FILE_NAME: &str = "keyword_vol_cache.json";
+
+/// A single cached lookup, keyed by the exact query parameters we'd otherwise
+/// send to the API. Stores the full `KeywordData` (not just `vol`) so a
+/// cache hit can still satisfy `--metrics`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct CacheEntry {
+    keyword: String,
+    country: String,
+    currency: String,
+    data_source: String,
+    data: KeywordData,
+    fetched_at: u64,
+}
+
+impl CacheEntry {
+    fn key(&self) -> String {
+        cache_key(&self.keyword, &self.country, &self.currency, &self.data_source)
+    }
+
+    fn is_fresh(&self, max_age: Duration, now: u64) -> bool {
+        now.saturating_sub(self.fetched_at) <= max_age.as_secs()
+    }
+}
+
+fn cache_key(keyword: &str, country: &str, currency: &str, data_source: &str) -> String {
+    format!("{keyword}\u{1}{country}\u{1}{currency}\u{1}{data_source}")
+}
+
+fn now_unix() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .expect("system clock is before the Unix epoch")
+        .as_secs()
+}
+
+/// On-disk cache of keyword -> search volume lookups, so re-running the tool
+/// on an edited file doesn't re-query keywords we already have a fresh answer
+/// for.
+pub struct Cache {
+    path: PathBuf,
+    entries: HashMap<String, CacheEntry>,
+}
+
+impl Cache {
+    /// Open the cache file under `cache_dir`, creating the directory if needed.
+    /// A missing file gives an empty cache; the file is written by `save`.
+    pub fn open(cache_dir: &Path) -> Result<Self> {
+        fs::create_dir_all(cache_dir)
+            .with_context(|| format!("Failed to create cache directory: {}", cache_dir.display()))?;
+        let path = cache_dir.join(CACHE_FILE_NAME);
+
+        let entries = if path.exists() {
+            let file = File::open(&path)
+                .with_context(|| format!("Failed to open cache file: {}", path.display()))?;
+            let list: Vec<CacheEntry> = serde_json::from_reader(file)
+                .with_context(|| format!("Failed to parse cache file: {}", path.display()))?;
+            list.into_iter().map(|entry| (entry.key(), entry)).collect()
+        } else {
+            HashMap::new()
+        };
+
+        Ok(Self { path, entries })
+    }
+
+    /// Look up a keyword, returning its cached `KeywordData` only if the
+    /// entry is still within `max_age`.
+    pub fn get(
+        &self,
+        keyword: &str,
+        country: &str,
+        currency: &str,
+        data_source: &str,
+        max_age: Duration,
+    ) -> Option<KeywordData> {
+        let key = cache_key(keyword, country, currency, data_source);
+        self.entries
+            .get(&key)
+            .filter(|entry| entry.is_fresh(max_age, now_unix()))
+            .map(|entry| entry.data.clone())
+    }
+
+    /// Record freshly fetched data, overwriting any stale entry for the same
+    /// query parameters.
+    pub fn insert(&mut self, data: &KeywordData, country: &str, currency: &str, data_source: &str) {
+        let entry = CacheEntry {
+            keyword: data.keyword.clone(),
+            country: country.to_string(),
+            currency: currency.to_string(),
+            data_source: data_source.to_string(),
+            data: data.clone(),
+            fetched_at: now_unix(),
+        };
+        self.entries.insert(entry.key(), entry);
+    }
+
+    /// Persist the cache back to disk.
+    pub fn save(&self) -> Result<()> {
+        let file = File::create(&self.path)
+            .with_context(|| format!("Failed to write cache file: {}", self.path.display()))?;
+        let list: Vec<&CacheEntry> = self.entries.values().collect();
+        serde_json::to_writer_pretty(file, &list)
+            .with_context(|| format!("Failed to serialize cache file: {}", self.path.display()))
+    }
+}
@@ -0,0 +1,106 @@
+use anyhow::Result;
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+
+/// Streaming compression to apply to an input/output file, independent of
+/// its document format (CSV/JSON/NDJSON).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum Compression {
+    None,
+    #[value(alias = "gz")]
+    Gzip,
+    #[value(alias = "zst")]
+    Zstd,
+    #[value(alias = "br")]
+    Brotli,
+}
+
+impl Compression {
+    /// Infer compression from `path`'s extension, preferring an explicit override.
+    pub fn detect(path: &Path, explicit: Option<Compression>) -> Compression {
+        if let Some(compression) = explicit {
+            return compression;
+        }
+
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some(ext) if ext.eq_ignore_ascii_case("gz") => Compression::Gzip,
+            Some(ext) if ext.eq_ignore_ascii_case("zst") => Compression::Zstd,
+            Some(ext) if ext.eq_ignore_ascii_case("br") => Compression::Brotli,
+            _ => Compression::None,
+        }
+    }
+}
+
+/// Strip a recognized compression suffix (`.gz`/`.zst`/`.br`) so format
+/// detection can look at the extension underneath, e.g. `keywords.csv.gz` -> `keywords.csv`.
+pub fn strip_compressed_extension(path: &Path) -> PathBuf {
+    match path.extension().and_then(|ext| ext.to_str()) {
+        Some(ext) if matches!(ext.to_ascii_lowercase().as_str(), "gz" | "zst" | "br") => {
+            path.with_extension("")
+        }
+        _ => path.to_path_buf(),
+    }
+}
+
+/// Open `path` for reading, transparently decompressing it if needed.
+pub fn open_reader(path: &Path, compression: Compression) -> Result<Box<dyn Read>> {
+    let file = crate::formats::open_input(path)?;
+    Ok(match compression {
+        Compression::None => Box::new(file),
+        Compression::Gzip => Box::new(flate2::read::GzDecoder::new(file)),
+        Compression::Zstd => Box::new(zstd::stream::read::Decoder::new(file)?),
+        Compression::Brotli => Box::new(brotli::Decompressor::new(file, 4096)),
+    })
+}
+
+/// Create `path` for writing, transparently compressing it if needed.
+pub fn create_writer(path: &Path, compression: Compression) -> Result<Box<dyn Write>> {
+    let file = crate::formats::create_output(path)?;
+    Ok(match compression {
+        Compression::None => Box::new(file),
+        Compression::Gzip => Box::new(flate2::write::GzEncoder::new(file, flate2::Compression::default())),
+        Compression::Zstd => Box::new(zstd::stream::write::Encoder::new(file, 0)?.auto_finish()),
+        Compression::Brotli => Box::new(brotli::CompressorWriter::new(file, 4096, 11, 22)),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn strip_compressed_extension_strips_recognized_suffixes() {
+        assert_eq!(
+            strip_compressed_extension(Path::new("keywords.csv.gz")),
+            PathBuf::from("keywords.csv")
+        );
+        assert_eq!(
+            strip_compressed_extension(Path::new("keywords.json.zst")),
+            PathBuf::from("keywords.json")
+        );
+        assert_eq!(
+            strip_compressed_extension(Path::new("keywords.ndjson.br")),
+            PathBuf::from("keywords.ndjson")
+        );
+    }
+
+    #[test]
+    fn strip_compressed_extension_is_case_insensitive() {
+        assert_eq!(
+            strip_compressed_extension(Path::new("keywords.csv.GZ")),
+            PathBuf::from("keywords.csv")
+        );
+    }
+
+    #[test]
+    fn strip_compressed_extension_leaves_uncompressed_paths_untouched() {
+        assert_eq!(
+            strip_compressed_extension(Path::new("keywords.csv")),
+            PathBuf::from("keywords.csv")
+        );
+        assert_eq!(
+            strip_compressed_extension(Path::new("keywords")),
+            PathBuf::from("keywords")
+        );
+    }
+}
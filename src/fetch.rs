@@ -0,0 +1,207 @@
+use anyhow::{Context, Result};
+use reqwest::blocking::Client;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+/// The API's own hard cap on keywords per request.
+const API_BATCH_CAP: usize = 100;
+
+// Default query parameters sent alongside every keyword (the server's
+// `country`/`currency`/`dataSource` query params override them per request);
+// also used verbatim as part of the cache key, since a cached volume is only
+// valid for the parameters it was fetched with.
+pub const COUNTRY: &str = "us";
+pub const CURRENCY: &str = "USD";
+pub const DATA_SOURCE: &str = "gkp";
+
+// Keywords Everywhere API response structure
+#[derive(Debug, Deserialize)]
+struct ApiResponse {
+    data: Vec<KeywordData>,
+    #[allow(dead_code)]
+    credits: Option<i64>,
+    #[allow(dead_code)]
+    time: Option<f64>,
+}
+
+/// Everything the API returns for one keyword. We keep the full struct
+/// around (rather than just `vol`) so `--metrics` can surface CPC,
+/// competition, and the monthly trend alongside the search volume. It also
+/// derives `Serialize` so the cache can persist it verbatim instead of
+/// dropping everything but `vol`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct KeywordData {
+    pub vol: Option<i32>,
+    pub keyword: String,
+    #[serde(default)]
+    pub cpc: Option<Cpc>,
+    pub competition: Option<f64>,
+    #[serde(default)]
+    pub trend: Vec<TrendData>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Cpc {
+    pub currency: String,
+    pub value: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TrendData {
+    pub month: String,
+    pub year: i32,
+    pub value: i32,
+}
+
+/// Pick a batch size that still spreads small inputs across every worker.
+/// We'd like to use the API's 100-keyword cap, but if that cap would leave
+/// most workers idle (`concurrency * cap > total`), shrink the batch so the
+/// keywords divide evenly across `concurrency` workers instead.
+pub fn batch_size(total_keywords: usize, concurrency: usize) -> usize {
+    let concurrency = concurrency.max(1);
+    if concurrency * API_BATCH_CAP > total_keywords {
+        std::cmp::max(1, total_keywords.div_ceil(concurrency))
+    } else {
+        API_BATCH_CAP
+    }
+    .min(API_BATCH_CAP)
+}
+
+/// Fetch full keyword data (volume, CPC, competition, trend) for every
+/// keyword, spreading batches across a bounded worker pool. Output ordering
+/// is the caller's responsibility: this only returns a keyword -> data map,
+/// which is order-independent by design.
+#[allow(clippy::too_many_arguments)]
+pub fn fetch_volumes(
+    client: &Client,
+    api_key: &str,
+    keywords: &[String],
+    country: &str,
+    currency: &str,
+    data_source: &str,
+    concurrency: usize,
+    verbose: bool,
+) -> Result<HashMap<String, KeywordData>> {
+    let endpoint = "https://api.keywordseverywhere.com/v1/get_keyword_data";
+    let batch_size = batch_size(keywords.len(), concurrency);
+    let chunks: Vec<&[String]> = keywords.chunks(batch_size).collect();
+    let total_batches = chunks.len();
+
+    if verbose {
+        println!(
+            "Fetching search volume data for {} keywords across {} batch(es) with concurrency {}...",
+            keywords.len(),
+            total_batches,
+            concurrency
+        );
+    }
+
+    let volumes = Arc::new(Mutex::new(HashMap::new()));
+
+    let pool = rayon::ThreadPoolBuilder::new()
+        .num_threads(concurrency.max(1))
+        .build()
+        .context("Failed to build worker pool")?;
+
+    pool.install(|| -> Result<()> {
+        use rayon::prelude::*;
+
+        chunks
+            .par_iter()
+            .enumerate()
+            .try_for_each(|(batch_index, keyword_chunk)| -> Result<()> {
+                if verbose {
+                    println!("Processing batch {}/{}", batch_index + 1, total_batches);
+                }
+
+                // Add each keyword as a separate kw[] parameter, alongside
+                // the locale/data-source params the volume was requested
+                // (and cached) under.
+                let mut params = vec![
+                    ("country", country),
+                    ("currency", currency),
+                    ("dataSource", data_source),
+                ];
+                for keyword in keyword_chunk.iter() {
+                    params.push(("kw[]", keyword.as_str()));
+                }
+
+                let response = client
+                    .post(endpoint)
+                    .header("Authorization", format!("Bearer {}", api_key))
+                    .header("Accept", "application/json")
+                    .form(&params)
+                    .send()
+                    .context("Failed to send request to Keywords Everywhere API")?;
+
+                if !response.status().is_success() {
+                    let status = response.status();
+                    let error_text = response.text()?;
+                    anyhow::bail!(
+                        "API request failed with status code: {}. Error: {}",
+                        status,
+                        error_text
+                    );
+                }
+
+                let response_text = response.text()?;
+
+                if verbose {
+                    println!("\nRaw API Response:\n{}", response_text);
+                }
+
+                let api_data: ApiResponse = serde_json::from_str(&response_text)
+                    .context("Failed to parse API response as JSON")?;
+
+                let mut volumes = volumes.lock().unwrap();
+                for kw_data in api_data.data {
+                    if verbose {
+                        let volume = kw_data.vol.map_or("N/A".to_string(), |v| v.to_string());
+                        println!("Keyword: {:40} | Search Volume: {}", kw_data.keyword, volume);
+                    }
+                    volumes.insert(kw_data.keyword.clone(), kw_data);
+                }
+
+                Ok(())
+            })
+    })?;
+
+    Ok(Arc::try_unwrap(volumes)
+        .expect("all worker threads have finished")
+        .into_inner()
+        .unwrap())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn batch_size_caps_at_the_api_limit() {
+        assert_eq!(batch_size(10_000, 4), API_BATCH_CAP);
+    }
+
+    #[test]
+    fn batch_size_shrinks_to_spread_small_inputs_across_workers() {
+        // 4 workers * 100-cap would cover 400 keywords, but there are only 10,
+        // so the batch should shrink to spread them evenly instead.
+        assert_eq!(batch_size(10, 4), 3);
+    }
+
+    #[test]
+    fn batch_size_is_exact_at_the_concurrency_boundary() {
+        // concurrency * cap == total: the cap itself still divides evenly.
+        assert_eq!(batch_size(400, 4), API_BATCH_CAP);
+    }
+
+    #[test]
+    fn batch_size_never_goes_below_one() {
+        assert_eq!(batch_size(1, 8), 1);
+    }
+
+    #[test]
+    fn batch_size_treats_zero_concurrency_as_one_worker() {
+        assert_eq!(batch_size(50, 0), 50);
+    }
+}
@@ -0,0 +1,412 @@
+use crate::fetch::KeywordData;
+use anyhow::{bail, Context, Result};
+use serde_json::{Map, Value};
+use std::collections::{BTreeSet, HashMap};
+use std::fs::File;
+use std::io::{BufRead, BufReader, Write};
+use std::path::Path;
+
+/// The on-disk shape of a keyword file, independent of how it's read or written.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum Format {
+    Csv,
+    Json,
+    #[value(alias = "jsonl")]
+    Ndjson,
+}
+
+impl Format {
+    /// Figure out which format to use for `path`, preferring an explicit override
+    /// and falling back to sniffing the file extension.
+    pub fn detect(path: &Path, explicit: Option<Format>) -> Result<Format> {
+        if let Some(format) = explicit {
+            return Ok(format);
+        }
+
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some(ext) if ext.eq_ignore_ascii_case("csv") => Ok(Format::Csv),
+            Some(ext) if ext.eq_ignore_ascii_case("json") => Ok(Format::Json),
+            Some(ext) if ext.eq_ignore_ascii_case("ndjson") || ext.eq_ignore_ascii_case("jsonl") => {
+                Ok(Format::Ndjson)
+            }
+            Some(ext) => bail!(
+                "Can't infer format from extension '.{ext}' for {}; pass --format explicitly",
+                path.display()
+            ),
+            None => bail!(
+                "File {} has no extension; pass --format explicitly",
+                path.display()
+            ),
+        }
+    }
+}
+
+/// A CSV document, holding the parsed headers/records so they can be written
+/// back out with the fetched columns merged in.
+pub struct CsvDocument {
+    pub headers: csv::StringRecord,
+    pub keyword_index: usize,
+    pub records: Vec<csv::StringRecord>,
+}
+
+impl CsvDocument {
+    pub fn read<R: std::io::Read>(reader: R) -> Result<Self> {
+        let mut rdr = csv::ReaderBuilder::new().has_headers(true).from_reader(reader);
+
+        let headers = rdr.headers().context("Failed to read CSV headers")?.clone();
+        let keyword_index = headers
+            .iter()
+            .position(|h| h == "Keyword")
+            .context("CSV must have a 'Keyword' column")?;
+
+        let mut records = Vec::new();
+        for result in rdr.records() {
+            records.push(result.context("Failed to read CSV row")?);
+        }
+
+        Ok(Self {
+            headers,
+            keyword_index,
+            records,
+        })
+    }
+
+    pub fn keywords(&self) -> Vec<String> {
+        self.records
+            .iter()
+            .map(|record| record[self.keyword_index].to_string())
+            .collect()
+    }
+
+    /// Write the document back out with `columns` merged in: columns that
+    /// already exist in the input are updated in place (left untouched where
+    /// there's no new data), columns that don't exist are appended.
+    pub fn write<W: Write>(
+        self,
+        writer: W,
+        columns: &[String],
+        data: &HashMap<String, KeywordData>,
+    ) -> Result<()> {
+        let mut wtr = csv::WriterBuilder::new().from_writer(writer);
+
+        let existing_index: Vec<Option<usize>> = columns
+            .iter()
+            .map(|column| self.headers.iter().position(|h| h == column))
+            .collect();
+
+        let mut new_headers = self.headers.clone();
+        for (column, index) in columns.iter().zip(&existing_index) {
+            if index.is_none() {
+                new_headers.push_field(column);
+            }
+        }
+        wtr.write_record(&new_headers)?;
+
+        for record in &self.records {
+            let keyword = &record[self.keyword_index];
+            let kw_data = data.get(keyword);
+            let mut fields: Vec<String> = record.iter().map(|s| s.to_string()).collect();
+
+            for (column, index) in columns.iter().zip(&existing_index) {
+                let value = column_value(column, kw_data);
+                match index {
+                    Some(i) => {
+                        if let Some(value) = value {
+                            fields[*i] = value;
+                        }
+                    }
+                    None => fields.push(value.unwrap_or_default()),
+                }
+            }
+
+            wtr.write_record(&csv::StringRecord::from(fields))?;
+        }
+
+        wtr.flush().context("Failed to flush CSV writer")?;
+        Ok(())
+    }
+}
+
+/// The output columns to emit: always "Search Volume", plus "CPC",
+/// "Competition", and one "Trend YYYY-MM" column per distinct month present
+/// in the fetched data (sorted chronologically) when `metrics` is set.
+pub fn output_columns(metrics: bool, data: &HashMap<String, KeywordData>) -> Vec<String> {
+    let mut columns = vec!["Search Volume".to_string()];
+    if !metrics {
+        return columns;
+    }
+
+    columns.push("CPC".to_string());
+    columns.push("Competition".to_string());
+
+    let mut months: BTreeSet<(i32, u32)> = BTreeSet::new();
+    for kw_data in data.values() {
+        for trend in &kw_data.trend {
+            months.insert((trend.year, month_number(&trend.month)));
+        }
+    }
+    for (year, month) in months {
+        columns.push(format!("Trend {year:04}-{month:02}"));
+    }
+
+    columns
+}
+
+/// The value for one output column, or `None` if there's no new data for it
+/// (in which case the caller should leave an already-existing cell as-is).
+fn column_value(column: &str, data: Option<&KeywordData>) -> Option<String> {
+    match column {
+        "Search Volume" => data.and_then(|d| d.vol).map(|vol| vol.to_string()),
+        "CPC" => data
+            .and_then(|d| d.cpc.as_ref())
+            .map(|cpc| format!("{} {}", cpc.currency, cpc.value)),
+        "Competition" => data.and_then(|d| d.competition).map(|c| c.to_string()),
+        trend_column if trend_column.starts_with("Trend ") => {
+            let (year, month) = trend_column["Trend ".len()..].split_once('-')?;
+            let year: i32 = year.parse().ok()?;
+            let month: u32 = month.parse().ok()?;
+            data.and_then(|d| {
+                d.trend
+                    .iter()
+                    .find(|trend| trend.year == year && month_number(&trend.month) == month)
+            })
+            .map(|trend| trend.value.to_string())
+        }
+        _ => None,
+    }
+}
+
+fn month_number(month: &str) -> u32 {
+    if let Ok(n) = month.trim().parse::<u32>() {
+        return n;
+    }
+    match month.trim().to_ascii_lowercase().as_str() {
+        "jan" | "january" => 1,
+        "feb" | "february" => 2,
+        "mar" | "march" => 3,
+        "apr" | "april" => 4,
+        "may" => 5,
+        "jun" | "june" => 6,
+        "jul" | "july" => 7,
+        "aug" | "august" => 8,
+        "sep" | "sept" | "september" => 9,
+        "oct" | "october" => 10,
+        "nov" | "november" => 11,
+        "dec" | "december" => 12,
+        _ => 0,
+    }
+}
+
+/// A JSON or NDJSON document: a list of arbitrary objects, each expected to carry
+/// a "keyword" field. Any other fields the caller had (tags, notes, ...) are kept
+/// as-is and written back untouched.
+pub struct JsonDocument {
+    pub rows: Vec<Map<String, Value>>,
+}
+
+impl JsonDocument {
+    pub fn read<R: std::io::Read>(reader: R, ndjson: bool) -> Result<Self> {
+        let rows = if ndjson {
+            let mut rows = Vec::new();
+            for line in BufReader::new(reader).lines() {
+                let line = line.context("Failed to read NDJSON line")?;
+                if line.trim().is_empty() {
+                    continue;
+                }
+                let value: Value =
+                    serde_json::from_str(&line).context("Failed to parse NDJSON line")?;
+                rows.push(object_or_err(value)?);
+            }
+            rows
+        } else {
+            let value: Value =
+                serde_json::from_reader(reader).context("Failed to parse JSON input")?;
+            let array = value
+                .as_array()
+                .context("JSON input must be an array of objects")?
+                .clone();
+            array
+                .into_iter()
+                .map(object_or_err)
+                .collect::<Result<Vec<_>>>()?
+        };
+
+        Ok(Self { rows })
+    }
+
+    pub fn keywords(&self) -> Result<Vec<String>> {
+        self.rows
+            .iter()
+            .map(|row| {
+                row.get("keyword")
+                    .and_then(Value::as_str)
+                    .map(str::to_string)
+                    .context("JSON row is missing a 'keyword' field")
+            })
+            .collect()
+    }
+
+    pub fn write<W: Write>(
+        mut self,
+        mut writer: W,
+        ndjson: bool,
+        metrics: bool,
+        data: &HashMap<String, KeywordData>,
+    ) -> Result<()> {
+        for row in &mut self.rows {
+            let keyword = row
+                .get("keyword")
+                .and_then(Value::as_str)
+                .map(str::to_string);
+            let kw_data = keyword.as_deref().and_then(|kw| data.get(kw));
+
+            match kw_data.and_then(|d| d.vol) {
+                Some(vol) => {
+                    row.insert("search_volume".to_string(), Value::from(vol));
+                }
+                None if !row.contains_key("search_volume") => {
+                    row.insert("search_volume".to_string(), Value::Null);
+                }
+                None => {}
+            }
+
+            if metrics {
+                match kw_data.and_then(|d| d.cpc.as_ref()) {
+                    Some(cpc) => {
+                        row.insert(
+                            "cpc".to_string(),
+                            serde_json::json!({ "currency": cpc.currency, "value": cpc.value }),
+                        );
+                    }
+                    None if !row.contains_key("cpc") => {
+                        row.insert("cpc".to_string(), Value::Null);
+                    }
+                    None => {}
+                }
+
+                match kw_data.and_then(|d| d.competition) {
+                    Some(competition) => {
+                        row.insert("competition".to_string(), Value::from(competition));
+                    }
+                    None if !row.contains_key("competition") => {
+                        row.insert("competition".to_string(), Value::Null);
+                    }
+                    None => {}
+                }
+
+                match kw_data.filter(|d| !d.trend.is_empty()) {
+                    Some(d) => {
+                        let trend_value: Vec<Value> = d
+                            .trend
+                            .iter()
+                            .map(|trend| serde_json::json!({ "month": trend.month, "year": trend.year, "value": trend.value }))
+                            .collect();
+                        row.insert("trend".to_string(), Value::Array(trend_value));
+                    }
+                    None if !row.contains_key("trend") => {
+                        row.insert("trend".to_string(), Value::Array(Vec::new()));
+                    }
+                    None => {}
+                }
+            }
+        }
+
+        if ndjson {
+            for row in &self.rows {
+                serde_json::to_writer(&mut writer, row)?;
+                writeln!(writer)?;
+            }
+        } else {
+            serde_json::to_writer_pretty(&mut writer, &self.rows)?;
+            writeln!(writer)?;
+        }
+
+        Ok(())
+    }
+}
+
+fn object_or_err(value: Value) -> Result<Map<String, Value>> {
+    match value {
+        Value::Object(map) => Ok(map),
+        other => bail!("Expected a JSON object row, got: {other}"),
+    }
+}
+
+pub fn open_input(path: &Path) -> Result<File> {
+    File::open(path).with_context(|| format!("Failed to open input file: {}", path.display()))
+}
+
+pub fn create_output(path: &Path) -> Result<File> {
+    File::create(path).with_context(|| format!("Failed to create output file: {}", path.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fetch::{Cpc, TrendData};
+
+    fn kw_data_with_trend(trend: Vec<TrendData>) -> KeywordData {
+        KeywordData {
+            vol: Some(100),
+            keyword: "shoes".to_string(),
+            cpc: Some(Cpc {
+                currency: "USD".to_string(),
+                value: "1.23".to_string(),
+            }),
+            competition: Some(0.5),
+            trend,
+        }
+    }
+
+    #[test]
+    fn month_number_parses_numeric_months() {
+        assert_eq!(month_number("7"), 7);
+        assert_eq!(month_number(" 12 "), 12);
+    }
+
+    #[test]
+    fn month_number_parses_month_names_case_insensitively() {
+        assert_eq!(month_number("Jan"), 1);
+        assert_eq!(month_number("SEPTEMBER"), 9);
+        assert_eq!(month_number("dec"), 12);
+    }
+
+    #[test]
+    fn month_number_falls_back_to_zero_for_garbage() {
+        assert_eq!(month_number("not-a-month"), 0);
+    }
+
+    #[test]
+    fn column_value_round_trips_a_trend_column() {
+        let data = kw_data_with_trend(vec![TrendData {
+            month: "Mar".to_string(),
+            year: 2024,
+            value: 42,
+        }]);
+        assert_eq!(column_value("Trend 2024-03", Some(&data)), Some("42".to_string()));
+    }
+
+    #[test]
+    fn column_value_is_none_for_a_trend_column_with_no_matching_month() {
+        let data = kw_data_with_trend(vec![TrendData {
+            month: "Mar".to_string(),
+            year: 2024,
+            value: 42,
+        }]);
+        assert_eq!(column_value("Trend 2024-04", Some(&data)), None);
+    }
+
+    #[test]
+    fn column_value_is_none_without_data() {
+        assert_eq!(column_value("Search Volume", None), None);
+        assert_eq!(column_value("Trend 2024-03", None), None);
+    }
+
+    #[test]
+    fn column_value_handles_search_volume_cpc_and_competition() {
+        let data = kw_data_with_trend(Vec::new());
+        assert_eq!(column_value("Search Volume", Some(&data)), Some("100".to_string()));
+        assert_eq!(column_value("CPC", Some(&data)), Some("USD 1.23".to_string()));
+        assert_eq!(column_value("Competition", Some(&data)), Some("0.5".to_string()));
+    }
+}
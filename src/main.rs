@@ -1,71 +1,81 @@
-use anyhow::{Context, Result};
-use clap::Parser;
+mod cache;
+mod compress;
+mod fetch;
+mod formats;
+mod server;
+
+use anyhow::{bail, Context, Result};
+use cache::Cache;
+use clap::{Parser, Subcommand};
+use compress::Compression;
 use dotenv::dotenv;
+use fetch::KeywordData;
+use formats::{CsvDocument, Format, JsonDocument};
 use reqwest::blocking::Client;
-use serde::{Deserialize, Serialize};
 use std::cmp::min;
+use std::collections::HashMap;
 use std::env;
-use std::fs::File;
 use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::Duration;
 
 // Command line arguments for the CLI tool
 #[derive(Parser, Debug)]
 #[command(author, version, about, long_about = None)]
 struct Args {
-    /// Input CSV file path
+    #[command(subcommand)]
+    command: Option<Command>,
+
+    /// Input file path (.csv, .json, or .ndjson/.jsonl); required unless running `serve`
     #[arg(short, long)]
-    input: PathBuf,
+    input: Option<PathBuf>,
 
-    /// Output CSV file path (defaults to overwriting input file if not specified)
+    /// Output file path (defaults to overwriting the input file if not specified)
     #[arg(short, long)]
     output: Option<PathBuf>,
 
-    /// Print detailed results to console
-    #[arg(short, long)]
-    verbose: bool,
-}
+    /// Force a specific input/output format instead of inferring it from the file extension
+    #[arg(long, value_enum)]
+    format: Option<Format>,
 
-// Structure for CSV input rows
-#[derive(Debug, Clone, Deserialize, Serialize)]
-struct KeywordRow {
-    #[serde(rename = "Keyword")]
-    keyword: String,
-    
-    #[serde(rename = "Search Volume")]
-    #[serde(default)]
-    search_volume: Option<i32>,
-}
+    /// Force a specific compression for the input file instead of inferring it from the .gz/.zst/.br extension
+    #[arg(long, value_enum)]
+    input_compress: Option<Compression>,
 
-// Keywords Everywhere API response structure
-#[derive(Debug, Deserialize)]
-struct ApiResponse {
-    data: Vec<KeywordData>,
-    credits: Option<i64>,
-    time: Option<f64>,
-}
+    /// Force a specific compression for the output file instead of inferring it from the .gz/.zst/.br extension
+    #[arg(long, value_enum)]
+    output_compress: Option<Compression>,
 
-#[derive(Debug, Deserialize)]
-struct KeywordData {
-    vol: Option<i32>,
-    keyword: String,
-    #[serde(default)]
-    cpc: Option<Cpc>,
-    competition: Option<f64>,
-    #[serde(default)]
-    trend: Vec<TrendData>,
-}
+    /// Maximum number of keyword batches to fetch concurrently
+    #[arg(short, long, default_value_t = 4)]
+    concurrency: usize,
 
-#[derive(Debug, Deserialize)]
-struct Cpc {
-    currency: String,
-    value: String,
+    /// Directory for the on-disk lookup cache; omit to disable caching
+    #[arg(long)]
+    cache_dir: Option<PathBuf>,
+
+    /// Maximum age in days of a cached lookup before it's considered stale
+    #[arg(long, default_value_t = 30)]
+    max_age: u64,
+
+    /// Also fetch and output CPC, competition, and monthly trend columns
+    #[arg(long)]
+    metrics: bool,
+
+    /// Print detailed results to console
+    #[arg(short, long)]
+    verbose: bool,
 }
 
-#[derive(Debug, Deserialize)]
-struct TrendData {
-    month: String,
-    year: i32,
-    value: i32,
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Run a long-lived HTTP server exposing GET /volume?kw=foo&kw=bar instead
+    /// of processing a single file
+    Serve {
+        /// Address to bind the HTTP server to
+        #[arg(long, default_value = "127.0.0.1:8080")]
+        bind: String,
+    },
 }
 
 fn main() -> Result<()> {
@@ -81,172 +91,157 @@ fn main() -> Result<()> {
         .context("KEYWORDS_EVERYWHERE_API_KEY not found in environment variables. Make sure to set it or create a .env file.")?;
     
     println!("Using API key: {}...", &api_key[0..min(5, api_key.len())]);
-    
-    // Open and read the CSV file
-    let file = File::open(&args.input)
-        .with_context(|| format!("Failed to open input file: {}", args.input.display()))?;
-    
-    let mut rdr = csv::ReaderBuilder::new()
-        .has_headers(true)
-        .from_reader(file);
-    
-    // Get headers to find keyword and search volume positions
-    let headers = rdr.headers().context("Failed to read CSV headers")?;
-    let keyword_index = headers.iter().position(|h| h == "Keyword")
-        .context("CSV must have a 'Keyword' column")?;
-    
-    // Search Volume column might not exist yet
-    let search_volume_index = headers.iter().position(|h| h == "Search Volume");
-    
-    // Clone headers to avoid borrow checker issues
-    let headers = headers.clone();
-    
-    // Store records and parsed keywords
-    let mut records: Vec<csv::StringRecord> = Vec::new();
-    let mut keywords: Vec<String> = Vec::new();
-    
-    // Read all records
-    for result in rdr.records() {
-        let record = result.context("Failed to read CSV row")?;
-        keywords.push(record[keyword_index].to_string());
-        records.push(record);
-    }
-    
-    // Create HTTP client
+
     let client = Client::new();
-    let endpoint = "https://api.keywordseverywhere.com/v1/get_keyword_data";
-    
-    println!("Fetching search volume data for {} keywords...", keywords.len());
-    
-    // Process keywords in batches (API limit is 100 keywords per request)
-    let batch_size = 100;
-    let total_batches = (keywords.len() + batch_size - 1) / batch_size;
-    
-    // Store the API results
-    let mut volumes: std::collections::HashMap<String, Option<i32>> = std::collections::HashMap::new();
-    
-    for (batch_index, keyword_chunk) in keywords.chunks(batch_size).enumerate() {
-        println!("Processing batch {}/{}", batch_index + 1, total_batches);
-        
-        // Create API request
-        let mut form = std::collections::HashMap::new();
-        form.insert("country", "us");
-        form.insert("currency", "USD");
-        form.insert("dataSource", "gkp");
-        
-        // Add each keyword as a separate kw[] parameter
-        let mut params = Vec::new();
-        for keyword in keyword_chunk {
-            params.push(("kw[]", keyword));
+
+    if let Some(Command::Serve { bind }) = args.command {
+        let cache = args
+            .cache_dir
+            .as_deref()
+            .map(Cache::open)
+            .transpose()?
+            .map(Mutex::new);
+        let config = server::ServerConfig {
+            bind,
+            concurrency: args.concurrency,
+            max_age: Duration::from_secs(args.max_age * 24 * 60 * 60),
+        };
+        return server::serve(config, client, api_key, cache);
+    }
+
+    let input = args
+        .input
+        .clone()
+        .context("--input is required unless running `serve`")?;
+
+    // Detect the input format and read the document into a format-agnostic
+    // list of keywords; the CSV/JSON representations themselves are kept
+    // around so we can merge volumes back in and write them out below.
+    // Compression is transparent to format detection: we sniff it off a
+    // `.gz`/`.zst`/`.br` suffix first and detect the format from what's left.
+    let input_compression = Compression::detect(&input, args.input_compress);
+    let input_format = Format::detect(&compress::strip_compressed_extension(&input), args.format)?;
+    let input_ndjson = matches!(input_format, Format::Ndjson);
+
+    enum Document {
+        Csv(CsvDocument),
+        Json(JsonDocument),
+    }
+
+    let input_reader = compress::open_reader(&input, input_compression)?;
+    let (document, keywords) = match input_format {
+        Format::Csv => {
+            let doc = CsvDocument::read(input_reader)?;
+            let keywords = doc.keywords();
+            (Document::Csv(doc), keywords)
         }
-        
-        let response = client.post(endpoint)
-            .header("Authorization", format!("Bearer {}", api_key))
-            .header("Accept", "application/json")
-            .form(&params)
-            .send()
-            .context("Failed to send request to Keywords Everywhere API")?;
-        
-        if !response.status().is_success() {
-            let status = response.status();
-            let error_text = response.text()?;
-            return Err(anyhow::anyhow!(
-                "API request failed with status code: {}. Error: {}", 
-                status, 
-                error_text
-            ));
+        Format::Json | Format::Ndjson => {
+            let doc = JsonDocument::read(input_reader, input_ndjson)?;
+            let keywords = doc.keywords()?;
+            (Document::Json(doc), keywords)
         }
-        
-        // Get the raw response text for verbose mode
-        let response_text = response.text()?;
-        
-        if args.verbose {
-            println!("\nRaw API Response:\n{}", response_text);
+    };
+
+    // Open the on-disk cache (if requested) and split keywords into ones we
+    // already have a fresh answer for and ones that still need fetching.
+    let mut cache = args.cache_dir.as_deref().map(Cache::open).transpose()?;
+    let max_age = Duration::from_secs(args.max_age * 24 * 60 * 60);
+
+    // `volumes` holds the full KeywordData we'll write into output columns;
+    // the cache stores the same full data, so a cache hit still satisfies
+    // `--metrics`.
+    let mut volumes: HashMap<String, KeywordData> = HashMap::new();
+    let mut to_fetch: Vec<String> = Vec::new();
+
+    for keyword in &keywords {
+        let cached = cache.as_ref().and_then(|cache| {
+            cache.get(keyword, fetch::COUNTRY, fetch::CURRENCY, fetch::DATA_SOURCE, max_age)
+        });
+        match cached {
+            Some(kw_data) => {
+                volumes.insert(keyword.clone(), kw_data);
+            }
+            None => to_fetch.push(keyword.clone()),
         }
-        
-        let api_data: ApiResponse = serde_json::from_str(&response_text)
-            .context("Failed to parse API response as JSON")?;
-        
-        // Update search volume for each keyword in the batch
-        for kw_data in api_data.data {
-            volumes.insert(kw_data.keyword.clone(), kw_data.vol);
-            
-            // Print volume info if verbose mode is enabled
-            if args.verbose {
-                let volume = kw_data.vol.map_or("N/A".to_string(), |v| v.to_string());
-                println!("Keyword: {:40} | Search Volume: {}", kw_data.keyword, volume);
+    }
+
+    if cache.is_some() {
+        println!(
+            "{} of {} keywords are already cached; fetching the remaining {}...",
+            keywords.len() - to_fetch.len(),
+            keywords.len(),
+            to_fetch.len()
+        );
+    }
+
+    // Fetch volumes concurrently across a bounded worker pool; keyword order
+    // in `records`/`keywords` is untouched, so output ordering stays
+    // deterministic even though the network phase runs out of order.
+    if !to_fetch.is_empty() {
+        let fetched = fetch::fetch_volumes(
+            &client,
+            &api_key,
+            &to_fetch,
+            fetch::COUNTRY,
+            fetch::CURRENCY,
+            fetch::DATA_SOURCE,
+            args.concurrency,
+            args.verbose,
+        )?;
+
+        if let Some(cache) = &mut cache {
+            for kw_data in fetched.values() {
+                cache.insert(kw_data, fetch::COUNTRY, fetch::CURRENCY, fetch::DATA_SOURCE);
             }
+            cache.save()?;
         }
+
+        volumes.extend(fetched);
     }
-    
+
     // Print summary of results if verbose mode is enabled
     if args.verbose {
         println!("\nSummary of Search Volumes:");
         println!("{:-^80}", "");
-        println!("{:40} | {}", "Keyword", "Search Volume");
+        println!("{:40} | Search Volume", "Keyword");
         println!("{:-^80}", "");
         
-        for (keyword, volume) in &volumes {
-            let volume_str = volume.map_or("N/A".to_string(), |v| v.to_string());
+        for (keyword, kw_data) in &volumes {
+            let volume_str = kw_data.vol.map_or("N/A".to_string(), |v| v.to_string());
             println!("{:40} | {}", keyword, volume_str);
         }
         println!("{:-^80}", "");
     }
-    
+
     // Determine output file path (use input file if output not specified)
-    let output_path = args.output.unwrap_or_else(|| args.input.clone());
-    
-    // Write updated data to CSV
-    let output_file = File::create(&output_path)
-        .with_context(|| format!("Failed to create output file: {}", output_path.display()))?;
-    
-    // Create a CSV writer
-    let mut wtr = csv::WriterBuilder::new()
-        .from_writer(output_file);
-    
-    // Create a new headers row with "Search Volume" if it doesn't exist
-    let mut new_headers = headers.clone();
-    if search_volume_index.is_none() {
-        new_headers.push_field("Search Volume");
+    let output_path = args.output.unwrap_or_else(|| input.clone());
+    let output_compression = Compression::detect(&output_path, args.output_compress);
+    let output_format = Format::detect(&compress::strip_compressed_extension(&output_path), args.format)?;
+    let output_ndjson = matches!(output_format, Format::Ndjson);
+
+    // CSV and JSON/NDJSON are read into entirely different document
+    // representations, so converting between the two isn't supported yet;
+    // catch a mismatched --output extension here instead of silently
+    // writing the wrong bytes into it. Toggling between JSON and NDJSON on
+    // the same document is fine, since they share `JsonDocument`.
+    let input_is_json_kind = matches!(input_format, Format::Json | Format::Ndjson);
+    let output_is_json_kind = matches!(output_format, Format::Json | Format::Ndjson);
+    if input_is_json_kind != output_is_json_kind {
+        bail!(
+            "Can't convert {:?} input to {:?} output; --input and --output must both be CSV or both be JSON/NDJSON",
+            input_format,
+            output_format
+        );
     }
-    
-    // Write the headers
-    wtr.write_record(&new_headers)?;
-    
-    // Write all records with updated search volume
-    for record in records {
-        let keyword = &record[keyword_index];
-        
-        if let Some(sv_index) = search_volume_index {
-            // If Search Volume column already exists, update it
-            let mut new_record = record.clone();
-            if let Some(volume) = volumes.get(keyword) {
-                if let Some(vol) = volume {
-                    // Create a completely new record as StringRecord doesn't have a get_mut method
-                    let mut fields: Vec<String> = new_record.iter().map(|s| s.to_string()).collect();
-                    fields[sv_index] = vol.to_string();
-                    new_record = csv::StringRecord::from(fields);
-                }
-            }
-            wtr.write_record(&new_record)?;
-        } else {
-            // If Search Volume column doesn't exist, add it
-            let mut new_record = record.clone();
-            if let Some(volume) = volumes.get(keyword) {
-                if let Some(vol) = volume {
-                    new_record.push_field(&vol.to_string());
-                } else {
-                    new_record.push_field("");
-                }
-            } else {
-                new_record.push_field("");
-            }
-            wtr.write_record(&new_record)?;
-        }
+
+    let output_writer = compress::create_writer(&output_path, output_compression)?;
+    let columns = formats::output_columns(args.metrics, &volumes);
+
+    match document {
+        Document::Csv(doc) => doc.write(output_writer, &columns, &volumes)?,
+        Document::Json(doc) => doc.write(output_writer, output_ndjson, args.metrics, &volumes)?,
     }
-    
-    wtr.flush().context("Failed to flush CSV writer")?;
-    
+
     println!("Successfully updated search volumes and saved to: {}", output_path.display());
     Ok(())
 }
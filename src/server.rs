@@ -0,0 +1,228 @@
+use crate::cache::Cache;
+use crate::fetch;
+use anyhow::{anyhow, Result};
+use reqwest::blocking::Client;
+use serde_json::json;
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::Duration;
+use tiny_http::{Header, Method, Response, Server};
+
+pub struct ServerConfig {
+    pub bind: String,
+    pub concurrency: usize,
+    pub max_age: Duration,
+}
+
+/// Run a long-lived HTTP daemon exposing `GET /volume?kw=foo&kw=bar&country=us`,
+/// which batches the requested keywords through the same fetch + cache path
+/// the file mode uses and returns e.g. `{"foo": 1200, "bar": null}`.
+pub fn serve(config: ServerConfig, client: Client, api_key: String, cache: Option<Mutex<Cache>>) -> Result<()> {
+    let server =
+        Server::http(&config.bind).map_err(|err| anyhow!("Failed to bind {}: {}", config.bind, err))?;
+    println!("Listening on http://{}", config.bind);
+
+    for request in server.incoming_requests() {
+        let (status, body) = handle_request(&request, &client, &api_key, &cache, &config)
+            .unwrap_or_else(|err| (500, json!({ "error": err.to_string() }).to_string()));
+
+        let header = Header::from_bytes(&b"Content-Type"[..], &b"application/json"[..])
+            .expect("static header is valid");
+        let response = Response::from_string(body)
+            .with_status_code(status)
+            .with_header(header);
+
+        if let Err(err) = request.respond(response) {
+            eprintln!("Failed to write HTTP response: {err}");
+        }
+    }
+
+    Ok(())
+}
+
+fn handle_request(
+    request: &tiny_http::Request,
+    client: &Client,
+    api_key: &str,
+    cache: &Option<Mutex<Cache>>,
+    config: &ServerConfig,
+) -> Result<(u16, String)> {
+    if request.method() != &Method::Get {
+        return Ok((405, json!({ "error": "only GET is supported" }).to_string()));
+    }
+
+    let (path, query) = split_url(request.url());
+    if path != "/volume" {
+        return Ok((404, json!({ "error": "unknown route; try /volume?kw=..." }).to_string()));
+    }
+
+    let params = parse_query(query);
+    let keywords: Vec<String> = params
+        .iter()
+        .filter(|(key, _)| key == "kw")
+        .map(|(_, value)| value.clone())
+        .collect();
+
+    if keywords.is_empty() {
+        return Ok((400, json!({ "error": "at least one ?kw= parameter is required" }).to_string()));
+    }
+
+    let country = query_param(&params, "country").unwrap_or(fetch::COUNTRY);
+    let currency = query_param(&params, "currency").unwrap_or(fetch::CURRENCY);
+    let data_source = query_param(&params, "dataSource").unwrap_or(fetch::DATA_SOURCE);
+
+    let mut volumes: HashMap<String, Option<i32>> = HashMap::new();
+    let mut to_fetch: Vec<String> = Vec::new();
+
+    if let Some(cache) = cache {
+        let cache = cache.lock().unwrap();
+        for keyword in &keywords {
+            match cache.get(keyword, country, currency, data_source, config.max_age) {
+                Some(kw_data) => {
+                    volumes.insert(keyword.clone(), kw_data.vol);
+                }
+                None => to_fetch.push(keyword.clone()),
+            }
+        }
+    } else {
+        to_fetch = keywords.clone();
+    }
+
+    if !to_fetch.is_empty() {
+        let fetched = fetch::fetch_volumes(
+            client,
+            api_key,
+            &to_fetch,
+            country,
+            currency,
+            data_source,
+            config.concurrency,
+            false,
+        )?;
+
+        if let Some(cache) = cache {
+            let mut cache = cache.lock().unwrap();
+            for kw_data in fetched.values() {
+                cache.insert(kw_data, country, currency, data_source);
+            }
+            cache.save()?;
+        }
+
+        volumes.extend(fetched.into_iter().map(|(keyword, kw_data)| (keyword, kw_data.vol)));
+    }
+
+    let body: HashMap<&str, Option<i32>> = keywords
+        .iter()
+        .map(|keyword| (keyword.as_str(), volumes.get(keyword).copied().flatten()))
+        .collect();
+
+    Ok((200, serde_json::to_string(&body)?))
+}
+
+/// The value of the last occurrence of `key` in a parsed query string.
+fn query_param<'a>(params: &'a [(String, String)], key: &str) -> Option<&'a str> {
+    params
+        .iter()
+        .rev()
+        .find(|(k, _)| k == key)
+        .map(|(_, value)| value.as_str())
+}
+
+fn split_url(url: &str) -> (&str, &str) {
+    match url.find('?') {
+        Some(idx) => (&url[..idx], &url[idx + 1..]),
+        None => (url, ""),
+    }
+}
+
+fn parse_query(query: &str) -> Vec<(String, String)> {
+    query
+        .split('&')
+        .filter(|pair| !pair.is_empty())
+        .map(|pair| {
+            let mut parts = pair.splitn(2, '=');
+            let key = parts.next().unwrap_or("");
+            let value = parts.next().unwrap_or("");
+            (percent_decode(key), percent_decode(value))
+        })
+        .collect()
+}
+
+fn percent_decode(s: &str) -> String {
+    let bytes = s.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'+' => {
+                out.push(b' ');
+                i += 1;
+            }
+            b'%' if i + 2 < bytes.len() => {
+                if let Ok(byte) = u8::from_str_radix(&s[i + 1..i + 3], 16) {
+                    out.push(byte);
+                    i += 3;
+                } else {
+                    out.push(bytes[i]);
+                    i += 1;
+                }
+            }
+            b => {
+                out.push(b);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&out).into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn percent_decode_decodes_percent_escapes_and_plus_as_space() {
+        assert_eq!(percent_decode("red+shoes%21"), "red shoes!");
+    }
+
+    #[test]
+    fn percent_decode_leaves_a_truncated_escape_literal() {
+        assert_eq!(percent_decode("100%"), "100%");
+        assert_eq!(percent_decode("100%2"), "100%2");
+    }
+
+    #[test]
+    fn percent_decode_leaves_invalid_hex_literal() {
+        assert_eq!(percent_decode("%zz"), "%zz");
+    }
+
+    #[test]
+    fn parse_query_splits_pairs_and_decodes_each_side() {
+        let params = parse_query("kw=red+shoes&kw=blue%20shoes&country=us");
+        assert_eq!(
+            params,
+            vec![
+                ("kw".to_string(), "red shoes".to_string()),
+                ("kw".to_string(), "blue shoes".to_string()),
+                ("country".to_string(), "us".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn parse_query_handles_a_valueless_key_and_empty_segments() {
+        let params = parse_query("kw=shoes&&flag=");
+        assert_eq!(
+            params,
+            vec![
+                ("kw".to_string(), "shoes".to_string()),
+                ("flag".to_string(), "".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn parse_query_of_an_empty_string_is_empty() {
+        assert!(parse_query("").is_empty());
+    }
+}